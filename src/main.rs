@@ -1,6 +1,7 @@
 mod telnet;
 
 use anyhow::Result;
+use std::time::Duration;
 use telnet::{
     connection::Connection,
     frame::TelnetFrame,
@@ -8,6 +9,9 @@ use telnet::{
 };
 use tokio::net::{TcpListener, TcpStream};
 
+// How long to wait for the client before sending a keepalive
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     println!("Listening on port 2323");
@@ -29,32 +33,54 @@ async fn main() -> Result<()> {
 }
 
 async fn process(socket: TcpStream) -> Result<()> {
-    let mut connection = Connection::new(socket);
+    let mut connection = Connection::new(socket)
+        .with_idle_timeout(IDLE_TIMEOUT)
+        .with_max_idle_intervals(3);
 
     // Enable server echo
     connection
-        .send_negotiation(TelnetAction::Will, TelnetOption::Echo)
+        .negotiate(TelnetAction::Will, TelnetOption::Echo)
         .await?;
 
     // Enable suppress go ahead
     connection
-        .send_negotiation(TelnetAction::Will, TelnetOption::SuppressGoAhead)
+        .negotiate(TelnetAction::Will, TelnetOption::SuppressGoAhead)
         .await?;
 
     // Ask the client to enable sending terminal type
     connection
-        .send_negotiation(TelnetAction::Do, TelnetOption::TerminalType)
+        .negotiate(TelnetAction::Do, TelnetOption::TerminalType)
         .await?;
 
     connection.request_terminal_type().await?;
 
+    // Offer MCCP2 so the client can ask us to compress downstream output
+    connection.offer_compression().await?;
+
     loop {
         match connection.next_frame().await {
+            Some(TelnetFrame::Negotiation(enabled))
+                if enabled.contains(&TelnetOption::Compress2)
+                    && !connection.compression_enabled() =>
+            {
+                connection.begin_compression().await?;
+            }
+            Some(TelnetFrame::Negotiation(enabled)) => {
+                println!("Negotiated options: {:?}", enabled);
+            }
             Some(TelnetFrame::IAC(iac)) => {
                 println!("Telnet IAC frame received: {:?}", iac);
             }
-            Some(TelnetFrame::CSI(f)) => {
-                println!("CSI frame received: {:?}", f);
+            Some(TelnetFrame::Subnegotiation(sub)) => {
+                println!(
+                    "Subnegotiation received: {:?} (window={:?}, terminal={:?})",
+                    sub,
+                    connection.window_size(),
+                    connection.terminal_type()
+                );
+            }
+            Some(TelnetFrame::CSI(event)) => {
+                println!("CSI event received: {:?}", event);
             }
             Some(TelnetFrame::Data(r)) => {
                 println!("Data frame received: {:?}", r);
@@ -62,6 +88,11 @@ async fn process(socket: TcpStream) -> Result<()> {
             }
             Some(TelnetFrame::Next) => {}
 
+            Some(TelnetFrame::Timeout) => {
+                println!("Closing idle connection");
+                return Ok(());
+            }
+
             // No frame available
             None => return Ok(()),
         }
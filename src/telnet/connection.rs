@@ -1,5 +1,8 @@
 use anyhow::Result;
 use bytes::BytesMut;
+use flate2::{write::ZlibEncoder, Compression};
+use std::io::Write;
+use std::time::Duration;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::TcpStream,
@@ -7,12 +10,28 @@ use tokio::{
 
 use super::{
     frame::TelnetFrame,
-    negotiation::{TelnetAction, TelnetOption},
+    negotiation::{Negotiator, TelnetAction, TelnetOption},
+    subnegotiation::{Subnegotiation, WindowSize},
 };
 
+// Default number of consecutive idle read intervals after which a
+// connection with an idle timeout set is disconnected.
+const DEFAULT_MAX_IDLE_INTERVALS: u32 = 3;
+
 pub struct Connection {
     stream: TcpStream,
     buffer: BytesMut,
+    negotiator: Negotiator,
+    window_size: Option<WindowSize>,
+    terminal_type: Option<String>,
+
+    // Once MCCP2 is negotiated, every payload passed to `send` is run
+    // through this deflate stream instead of written raw.
+    compressor: Option<ZlibEncoder<Vec<u8>>>,
+
+    idle_timeout: Option<Duration>,
+    max_idle_intervals: u32,
+    idle_intervals: u32,
 }
 
 impl Connection {
@@ -20,14 +39,71 @@ impl Connection {
         Self {
             stream,
             buffer: BytesMut::with_capacity(4096),
+            negotiator: Negotiator::new(),
+            window_size: None,
+            terminal_type: None,
+            compressor: None,
+            idle_timeout: None,
+            max_idle_intervals: DEFAULT_MAX_IDLE_INTERVALS,
+            idle_intervals: 0,
         }
     }
 
+    /// Wait at most this long on each read for the client to send anything.
+    /// Each lapse counts as one idle interval: the connection ends at the
+    /// `max_idle_intervals`-th lapse in a row, and every earlier lapse sends
+    /// a keepalive and starts the wait again (see `with_max_idle_intervals`).
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// How many consecutive idle intervals end the connection. Every idle
+    /// interval before the last sends a keepalive; the last makes
+    /// `next_frame` return `TelnetFrame::Timeout`.
+    pub fn with_max_idle_intervals(mut self, max: u32) -> Self {
+        self.max_idle_intervals = max;
+        self
+    }
+
+    /// The client's last-reported terminal dimensions (via NAWS), if any.
+    pub fn window_size(&self) -> Option<WindowSize> {
+        self.window_size
+    }
+
+    /// The client's reported terminal type, if any.
+    pub fn terminal_type(&self) -> Option<&str> {
+        self.terminal_type.as_deref()
+    }
+
     pub async fn request_terminal_type(&mut self) -> Result<()> {
-        self.stream.write_all(&[0xFF, 253, 24]).await?;
-        self.stream
-            .write_all(&[0xFF, 250, 24, 1, 0xFF, 240])
-            .await?;
+        self.write_bytes(&[0xFF, 253, 24]).await?;
+        self.write_bytes(&[0xFF, 250, 24, 1, 0xFF, 240]).await?;
+
+        Ok(())
+    }
+
+    /// Offer MCCP2 downstream compression (`IAC WILL COMPRESS2`). If the
+    /// client accepts, `next_frame` resolves it into a `Negotiation` frame
+    /// carrying `TelnetOption::Compress2`, and the caller should invoke
+    /// `begin_compression`.
+    pub async fn offer_compression(&mut self) -> Result<()> {
+        self.negotiate(TelnetAction::Will, TelnetOption::Compress2)
+            .await
+    }
+
+    /// Whether MCCP2 compression is currently engaged on this connection.
+    pub fn compression_enabled(&self) -> bool {
+        self.compressor.is_some()
+    }
+
+    /// Send the MCCP2 marker subnegotiation (`IAC SB COMPRESS2 IAC SE`)
+    /// uncompressed, then switch all subsequent `send` calls onto a zlib
+    /// deflate stream. Compression begins with the first byte *after* the
+    /// marker's trailing SE, never the marker itself.
+    pub async fn begin_compression(&mut self) -> Result<()> {
+        self.write_bytes(&[0xFF, 250, 86, 0xFF, 240]).await?;
+        self.compressor = Some(ZlibEncoder::new(Vec::new(), Compression::default()));
 
         Ok(())
     }
@@ -40,7 +116,16 @@ impl Connection {
         let action = num::ToPrimitive::to_u8(&action).unwrap();
         let option = num::ToPrimitive::to_u8(&option).unwrap();
 
-        self.stream.write_all(&[0xFF, action, option]).await?;
+        self.write_bytes(&[0xFF, action, option]).await
+    }
+
+    /// Locally request that an option be enabled/disabled, routed through
+    /// the RFC 1143 state machine so a loop doesn't send WILL/DO more than
+    /// once while a prior request is still outstanding.
+    pub async fn negotiate(&mut self, action: TelnetAction, option: TelnetOption) -> Result<()> {
+        if let Some(action) = self.negotiator.request(action, option) {
+            self.send_negotiation(action, option).await?;
+        }
 
         Ok(())
     }
@@ -50,28 +135,161 @@ impl Connection {
             .or_else(|| TelnetFrame::parse_csi(&mut self.buffer))
             .or_else(|| TelnetFrame::parse_data(&mut self.buffer))
         {
+            Some(TelnetFrame::IAC(iac)) => match self.handle_negotiation(&iac).await {
+                Some(frame) => Some(frame),
+                None => Some(TelnetFrame::IAC(iac)),
+            },
+
+            Some(TelnetFrame::Subnegotiation(sub)) => {
+                match &sub {
+                    Subnegotiation::WindowSize(size) => self.window_size = Some(*size),
+                    Subnegotiation::TerminalType(name) => self.terminal_type = Some(name.clone()),
+                    Subnegotiation::Unknown(_) => {}
+                }
+
+                Some(TelnetFrame::Subnegotiation(sub))
+            }
+
             // Some sort of frame exists, return it
             Some(frame) => Some(frame),
 
-            None => {
-                // If we didn't find a frame, try to read more data from the socket
-                match self.stream.read_buf(&mut self.buffer).await {
-                    // Reading 0 bytes means the socket has been closed by the client
-                    Ok(0) => None,
+            // If we didn't find a frame, try to read more data from the socket
+            None => match self.idle_timeout {
+                Some(timeout) => self.read_with_idle_timeout(timeout).await,
+                None => Self::read_result(self.stream.read_buf(&mut self.buffer).await),
+            },
+        }
+    }
+
+    /// Read with a deadline, sending a telnet keepalive and resetting it
+    /// each time the deadline lapses, and giving up only after
+    /// `max_idle_intervals` consecutive idle intervals.
+    async fn read_with_idle_timeout(&mut self, timeout: Duration) -> Option<TelnetFrame> {
+        match tokio::time::timeout(timeout, self.stream.read_buf(&mut self.buffer)).await {
+            Ok(result) => {
+                self.idle_intervals = 0;
+                Self::read_result(result)
+            }
 
-                    // Reading some bytes means we should try to parse again
-                    Ok(_) => Some(TelnetFrame::Next),
+            // Deadline elapsed without the client sending anything
+            Err(_) => {
+                self.idle_intervals += 1;
 
-                    // Reading failed, so we assume the socket has been closed
-                    Err(_) => None,
+                if self.idle_intervals >= self.max_idle_intervals {
+                    return Some(TelnetFrame::Timeout);
                 }
+
+                // IAC NOP keepalive
+                if self.write_bytes(&[0xFF, 241]).await.is_err() {
+                    return None;
+                }
+
+                Some(TelnetFrame::Next)
             }
         }
     }
 
+    fn read_result(result: std::io::Result<usize>) -> Option<TelnetFrame> {
+        match result {
+            // Reading 0 bytes means the socket has been closed by the client
+            Ok(0) => None,
+
+            // Reading some bytes means we should try to parse again
+            Ok(_) => Some(TelnetFrame::Next),
+
+            // Reading failed, so we assume the socket has been closed
+            Err(_) => None,
+        }
+    }
+
+    /// If `iac` is a WILL/WONT/DO/DONT negotiation, resolve it against the
+    /// Q-method state machine, send any reply it calls for, and return the
+    /// resulting `Negotiation` frame. Returns `None` when the command byte is
+    /// not WILL/WONT/DO/DONT or the option is not a known `TelnetOption`,
+    /// leaving it to the caller.
+    async fn handle_negotiation(&mut self, iac: &[u8]) -> Option<TelnetFrame> {
+        let (&action_byte, &option_byte) = match iac {
+            [0xFF, action, option] => (action, option),
+            _ => return None,
+        };
+
+        let action: TelnetAction = num::FromPrimitive::from_u8(action_byte)?;
+        let option: TelnetOption = num::FromPrimitive::from_u8(option_byte)?;
+
+        // We support every option we know about, whether or not we asked
+        // for it first.
+        if let Some(reply) = self.negotiator.receive(action, option, true) {
+            self.send_negotiation(reply, option).await.ok()?;
+        }
+
+        Some(TelnetFrame::Negotiation(self.negotiator.enabled()))
+    }
+
     pub async fn send(&mut self, data: &[u8]) -> Result<()> {
-        self.stream.write_all(data).await?;
+        self.write_bytes(data).await
+    }
+
+    /// Write raw bytes to the client, running them through the MCCP2
+    /// deflate stream first if compression is engaged. Every byte the
+    /// server sends after `begin_compression` — payload data, negotiation
+    /// replies, keepalives — must go through here rather than straight to
+    /// `self.stream`, or it corrupts the compressed stream the client is
+    /// inflating.
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<()> {
+        match &mut self.compressor {
+            Some(encoder) => {
+                let compressed = deflate_chunk(encoder, data)?;
+                self.stream.write_all(&compressed).await?;
+            }
+            None => {
+                self.stream.write_all(data).await?;
+            }
+        }
 
         Ok(())
     }
 }
+
+/// Run `data` through `encoder` and flush, returning just the bytes
+/// produced by this call. A sync flush after every write lets the client
+/// decode each payload as soon as it arrives, rather than buffering until
+/// the deflate stream is closed.
+fn deflate_chunk(encoder: &mut ZlibEncoder<Vec<u8>>, data: &[u8]) -> Result<Vec<u8>> {
+    encoder.write_all(data)?;
+    encoder.flush()?;
+
+    Ok(std::mem::take(encoder.get_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn deflate_chunk_flushes_output_on_every_write() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+
+        let first = deflate_chunk(&mut encoder, b"hello").unwrap();
+        let second = deflate_chunk(&mut encoder, b"world").unwrap();
+
+        // The zlib header only appears once, at the start of the stream,
+        // so the two chunks must be inflated as one continuous stream
+        // (exactly how the client does it), but each flush's worth of
+        // output must be readable without waiting for the next.
+        let mut combined = first.clone();
+        combined.extend_from_slice(&second);
+
+        let mut decoded = String::new();
+        ZlibDecoder::new(combined.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "helloworld");
+
+        // Each flush must have actually produced output of its own, not
+        // buffered everything for a later flush.
+        assert!(!first.is_empty());
+        assert!(!second.is_empty());
+    }
+}
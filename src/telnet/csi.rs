@@ -0,0 +1,151 @@
+/// A decoded Control Sequence Introducer (`ESC [ ... <final>`) sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsiEvent {
+    CursorUp(u16),
+    CursorDown(u16),
+    CursorForward(u16),
+    CursorBack(u16),
+
+    // Absolute cursor positioning (1-indexed row/column)
+    CursorPosition { row: u16, col: u16 },
+
+    EraseInDisplay(u16),
+    EraseInLine(u16),
+
+    Sgr(Vec<SgrAttribute>),
+
+    // A final byte we don't model; carries the full raw sequence
+    Unknown(Vec<u8>),
+}
+
+/// A single Select Graphic Rendition attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgrAttribute {
+    Reset,
+    Bold,
+    Underline,
+    Foreground(u8),
+    Background(u8),
+    Other(u16),
+}
+
+/// Decode a complete CSI sequence. `raw` is the full `ESC [ ... <final>`
+/// bytes (used for the `Unknown` fallback); `params` is the bytes between
+/// `ESC [` and the final byte; `final_byte` is the terminating byte
+/// (0x40-0x7E).
+pub fn decode(raw: &[u8], params: &[u8], final_byte: u8) -> CsiEvent {
+    let values = parse_params(params);
+
+    match final_byte {
+        b'A' => CsiEvent::CursorUp(count_or_default(&values)),
+        b'B' => CsiEvent::CursorDown(count_or_default(&values)),
+        b'C' => CsiEvent::CursorForward(count_or_default(&values)),
+        b'D' => CsiEvent::CursorBack(count_or_default(&values)),
+
+        b'H' | b'f' => CsiEvent::CursorPosition {
+            row: position_or_default(values.first().copied()),
+            col: position_or_default(values.get(1).copied()),
+        },
+
+        b'J' => CsiEvent::EraseInDisplay(values.first().copied().unwrap_or(0)),
+        b'K' => CsiEvent::EraseInLine(values.first().copied().unwrap_or(0)),
+
+        // An empty parameter list (bare `ESC[m`) means "reset all
+        // attributes", same as an explicit `0` — without this, a consumer
+        // can't tell "no SGR codes sent" from "client asked to reset".
+        b'm' if values.is_empty() => CsiEvent::Sgr(vec![SgrAttribute::Reset]),
+        b'm' => CsiEvent::Sgr(values.iter().map(|&v| decode_sgr(v)).collect()),
+
+        _ => CsiEvent::Unknown(raw.to_vec()),
+    }
+}
+
+fn parse_params(params: &[u8]) -> Vec<u16> {
+    if params.is_empty() {
+        return Vec::new();
+    }
+
+    params
+        .split(|&b| b == b';')
+        .map(|chunk| std::str::from_utf8(chunk).ok().and_then(|s| s.parse().ok()).unwrap_or(0))
+        .collect()
+}
+
+// Cursor movement counts default to, and treat 0 as, 1
+fn count_or_default(values: &[u16]) -> u16 {
+    match values.first() {
+        None | Some(0) => 1,
+        Some(&n) => n,
+    }
+}
+
+// Row/column positions default to, and treat 0 as, 1
+fn position_or_default(value: Option<u16>) -> u16 {
+    match value {
+        None | Some(0) => 1,
+        Some(n) => n,
+    }
+}
+
+fn decode_sgr(value: u16) -> SgrAttribute {
+    match value {
+        0 => SgrAttribute::Reset,
+        1 => SgrAttribute::Bold,
+        4 => SgrAttribute::Underline,
+        30..=37 => SgrAttribute::Foreground((value - 30) as u8),
+        40..=47 => SgrAttribute::Background((value - 40) as u8),
+        _ => SgrAttribute::Other(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_cursor_movement() {
+        assert_eq!(decode(b"\x1b[A", b"", b'A'), CsiEvent::CursorUp(1));
+        assert_eq!(decode(b"\x1b[5B", b"5", b'B'), CsiEvent::CursorDown(5));
+        assert_eq!(decode(b"\x1b[0C", b"0", b'C'), CsiEvent::CursorForward(1));
+    }
+
+    #[test]
+    fn test_decode_cursor_position() {
+        assert_eq!(
+            decode(b"\x1b[1;2H", b"1;2", b'H'),
+            CsiEvent::CursorPosition { row: 1, col: 2 }
+        );
+        assert_eq!(
+            decode(b"\x1b[H", b"", b'H'),
+            CsiEvent::CursorPosition { row: 1, col: 1 }
+        );
+    }
+
+    #[test]
+    fn test_decode_erase() {
+        assert_eq!(decode(b"\x1b[2J", b"2", b'J'), CsiEvent::EraseInDisplay(2));
+        assert_eq!(decode(b"\x1b[K", b"", b'K'), CsiEvent::EraseInLine(0));
+    }
+
+    #[test]
+    fn test_decode_sgr() {
+        assert_eq!(
+            decode(b"\x1b[1;31;44m", b"1;31;44", b'm'),
+            CsiEvent::Sgr(vec![
+                SgrAttribute::Bold,
+                SgrAttribute::Foreground(1),
+                SgrAttribute::Background(4),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_decode_sgr_empty_params_resets() {
+        assert_eq!(decode(b"\x1b[m", b"", b'm'), CsiEvent::Sgr(vec![SgrAttribute::Reset]));
+    }
+
+    #[test]
+    fn test_decode_unknown_final() {
+        assert_eq!(decode(b"\x1b[1p", b"1", b'p'), CsiEvent::Unknown(b"\x1b[1p".to_vec()));
+    }
+}
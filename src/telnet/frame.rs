@@ -1,18 +1,33 @@
 use bytes::{Buf, BytesMut};
 
+use super::csi::{self, CsiEvent};
+use super::negotiation::TelnetOption;
+use super::subnegotiation::{self, Subnegotiation};
+
 #[derive(Debug, PartialEq)]
 pub enum TelnetFrame {
     // Interpret as Command (IAC) telnet command
     IAC(Vec<u8>),
 
-    // Control Sequence Introducer (ANSI Escape sequence)
-    CSI(Vec<u8>),
+    // A WILL/WONT/DO/DONT negotiation was resolved; carries the full set
+    // of options currently enabled, on either side, after processing it
+    Negotiation(Vec<TelnetOption>),
+
+    // A decoded IAC SB ... IAC SE subnegotiation
+    Subnegotiation(Subnegotiation),
+
+    // A decoded Control Sequence Introducer (ANSI Escape sequence)
+    CSI(CsiEvent),
 
     // Data frame contains raw bytes
     Data(Vec<u8>),
 
     // No frame available, but more data may be coming
     Next,
+
+    // The connection has gone idle for too many consecutive timeout
+    // intervals and should be closed
+    Timeout,
 }
 
 impl TelnetFrame {
@@ -26,21 +41,7 @@ impl TelnetFrame {
             }
 
             // IAC + SB + ... + SE
-            (Some(0xFF), Some(&0xFA), _) => {
-                // Needle is equivalent to IAC + SE
-                let needle = &[0xFF, 0xF0];
-
-                // Find it in the "haystack"
-                match buffer.windows(2).position(|p| p == needle) {
-                    Some(i) => {
-                        // We found the subsequence, so split after it (including the needle)
-                        let iac = buffer.split_to(i + 2).to_vec();
-
-                        return Some(Self::IAC(iac));
-                    }
-                    None => None,
-                }
-            }
+            (Some(0xFF), Some(&0xFA), _) => subnegotiation::parse(buffer).map(Self::Subnegotiation),
 
             // IAC + WILL/WONT/DO/DONT + OPTION
             (Some(0xFF), Some(_), Some(_)) => {
@@ -66,8 +67,11 @@ impl TelnetFrame {
             .map(|i| {
                 // If we found the CSI final byte, return everything up to and
                 // including it
-                let csi = buffer.split_to(i + 3);
-                Self::CSI(csi.to_vec())
+                let raw = buffer.split_to(i + 3);
+                let final_byte = raw[raw.len() - 1];
+                let params = &raw[2..raw.len() - 1];
+
+                Self::CSI(csi::decode(&raw, params, final_byte))
             })
     }
 
@@ -104,13 +108,10 @@ mod tests {
 
     #[test]
     fn test_parse_iac() {
-        // Test IAC + SB + ... + SE
-        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 0x01, 0x02, 0xF0, 0xFF][..]);
+        // Test IAC + SB + ... + SE (decoded via the subnegotiation parser)
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 0x01, 0x02, 0xFF, 0xF0, 0xFF][..]);
         let result = TelnetFrame::parse_iac(&mut buffer);
-        assert!(matches!(result, Some(TelnetFrame::IAC(_))));
-        if let Some(TelnetFrame::IAC(iac)) = result {
-            assert_eq!(iac, vec![0xFF, 0xFA, 0x01, 0x02, 0xF0]);
-        }
+        assert!(matches!(result, Some(TelnetFrame::Subnegotiation(_))));
         assert_eq!(buffer, BytesMut::from(&[0xFF][..]));
 
         // Test IAC + WILL/WONT/DO/DONT + OPTION
@@ -176,12 +177,13 @@ mod tests {
 
     #[test]
     fn test_parse_csi() {
+        // ESC [ 1;2 H -> absolute cursor position (row 1, col 2)
         let mut buffer = BytesMut::from(&[0x1B, b'[', 0x31, 0x3B, 0x32, 0x48][..]);
         let result = TelnetFrame::parse_csi(&mut buffer);
-        assert!(matches!(result, Some(TelnetFrame::CSI(_))));
-        if let Some(TelnetFrame::CSI(csi)) = result {
-            assert_eq!(csi, vec![0x1B, b'[', 0x31, 0x3B, 0x32, 0x48]);
-        }
+        assert_eq!(
+            result,
+            Some(TelnetFrame::CSI(CsiEvent::CursorPosition { row: 1, col: 2 }))
+        );
 
         let mut buffer = BytesMut::from(&[0x1B, b'[', 0x31, 0x3B, 0x32][..]);
         let result = TelnetFrame::parse_csi(&mut buffer);
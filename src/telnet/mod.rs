@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod csi;
+pub mod frame;
+pub mod negotiation;
+pub mod subnegotiation;
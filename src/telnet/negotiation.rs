@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use num_derive::{FromPrimitive, ToPrimitive};
 
-#[derive(FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum TelnetAction {
     Will = 251,
     Wont = 252,
@@ -8,12 +10,323 @@ pub enum TelnetAction {
     Dont = 254,
 }
 
-#[derive(FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum TelnetOption {
     Echo = 1,
     SuppressGoAhead = 3,
     TerminalType = 24,
     WindowSize = 31,
+    Compress2 = 86,
     Subnegotiation = 250,
     SubnegotiationEnd = 240,
 }
+
+/// Which half of an option negotiation a state tracks: whether *we* do
+/// something (WILL/WONT) or whether *the peer* does something (DO/DONT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Us,
+    Him,
+}
+
+impl Side {
+    fn opposite(self) -> Side {
+        match self {
+            Side::Us => Side::Him,
+            Side::Him => Side::Us,
+        }
+    }
+}
+
+/// Per RFC 1143 (the "Q method"), each side of an option negotiation is one
+/// of four states, with an extra bit recording a queued request for the
+/// opposite of whatever we're currently waiting to resolve. This is what
+/// keeps a peer that echoes our WILL/DO back at us from driving the
+/// connection into an infinite WILL/WONT loop.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum QState {
+    #[default]
+    No,
+    Yes,
+    WantNo { queued: bool },
+    WantYes { queued: bool },
+}
+
+#[derive(Debug, Default)]
+struct OptionState {
+    us: QState,
+    him: QState,
+}
+
+/// Tracks telnet option negotiation state per RFC 1143.
+#[derive(Debug, Default)]
+pub struct Negotiator {
+    options: HashMap<TelnetOption, OptionState>,
+}
+
+impl Negotiator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A local request to enable or disable an option, e.g. offering
+    /// `Will`/`Wont` or asking the peer for `Do`/`Dont`. Sends only when the
+    /// request changes a settled `No`/`Yes` state. While a negotiation is
+    /// outstanding, a request for the opposite is queued until the peer
+    /// answers; `None` is returned whenever nothing is to be sent.
+    pub fn request(&mut self, action: TelnetAction, option: TelnetOption) -> Option<TelnetAction> {
+        let side = side_for(action);
+        let enable = agrees(action);
+        let state = self.state_mut(side, option);
+
+        match (*state, enable) {
+            (QState::No, true) => {
+                *state = QState::WantYes { queued: false };
+                Some(enable_action(side))
+            }
+            (QState::Yes, false) => {
+                *state = QState::WantNo { queued: false };
+                Some(disable_action(side))
+            }
+            (QState::WantNo { .. }, true) => {
+                *state = QState::WantNo { queued: true };
+                None
+            }
+            (QState::WantYes { .. }, false) => {
+                *state = QState::WantYes { queued: true };
+                None
+            }
+            // Asking again for what is already pending cancels a queued
+            // opposite request.
+            (QState::WantNo { queued: true }, false) => {
+                *state = QState::WantNo { queued: false };
+                None
+            }
+            (QState::WantYes { queued: true }, true) => {
+                *state = QState::WantYes { queued: false };
+                None
+            }
+            // Already in, or already converging to, the requested state.
+            _ => None,
+        }
+    }
+
+    /// Handle a negotiation command received from the peer, returning the
+    /// reply to send (if any). `accept` decides whether we agree to enable
+    /// an option the peer asked us to turn on when we hadn't asked first.
+    pub fn receive(
+        &mut self,
+        action: TelnetAction,
+        option: TelnetOption,
+        accept: bool,
+    ) -> Option<TelnetAction> {
+        // Inverted relative to `request`: a received Will/Wont is the peer
+        // telling us what *it* will do, so it resolves our `him` state (and
+        // our reply, if any, is a Do/Dont); a received Do/Dont asks what
+        // *we* will do, resolving `us` (replying with Will/Wont).
+        let side = side_for(action).opposite();
+        let requesting_enable = agrees(action);
+        let state = self.state_mut(side, option);
+
+        match (*state, requesting_enable) {
+            (QState::No, true) => {
+                if accept {
+                    *state = QState::Yes;
+                    Some(enable_action(side))
+                } else {
+                    Some(disable_action(side))
+                }
+            }
+            (QState::No, false) => None,
+
+            // Already enabled and the peer asked us to enable again:
+            // say nothing, which is what suppresses the WILL/WILL loop.
+            (QState::Yes, true) => None,
+            (QState::Yes, false) => {
+                *state = QState::No;
+                Some(disable_action(side))
+            }
+
+            // We asked to disable and the peer answered with an enable,
+            // so this is a protocol error; reset rather than trust it.
+            (QState::WantNo { queued: false }, true) => {
+                *state = QState::No;
+                None
+            }
+            (QState::WantNo { queued: true }, true) => {
+                *state = QState::Yes;
+                None
+            }
+            // Disable confirmed; now send the queued enable request.
+            (QState::WantNo { queued: true }, false) => {
+                *state = QState::WantYes { queued: false };
+                Some(enable_action(side))
+            }
+            (QState::WantNo { queued: false }, false) => {
+                *state = QState::No;
+                None
+            }
+
+            (QState::WantYes { queued: false }, true) => {
+                *state = QState::Yes;
+                None
+            }
+            (QState::WantYes { queued: true }, true) => {
+                *state = QState::WantNo { queued: false };
+                Some(disable_action(side))
+            }
+            (QState::WantYes { .. }, false) => {
+                *state = QState::No;
+                None
+            }
+        }
+    }
+
+    /// The options currently negotiated on, on either side.
+    pub fn enabled(&self) -> Vec<TelnetOption> {
+        self.options
+            .iter()
+            .filter(|(_, state)| state.us == QState::Yes || state.him == QState::Yes)
+            .map(|(option, _)| *option)
+            .collect()
+    }
+
+    fn state_mut(&mut self, side: Side, option: TelnetOption) -> &mut QState {
+        let entry = self.options.entry(option).or_default();
+        match side {
+            Side::Us => &mut entry.us,
+            Side::Him => &mut entry.him,
+        }
+    }
+}
+
+fn side_for(action: TelnetAction) -> Side {
+    match action {
+        TelnetAction::Will | TelnetAction::Wont => Side::Us,
+        TelnetAction::Do | TelnetAction::Dont => Side::Him,
+    }
+}
+
+fn agrees(action: TelnetAction) -> bool {
+    matches!(action, TelnetAction::Will | TelnetAction::Do)
+}
+
+fn enable_action(side: Side) -> TelnetAction {
+    match side {
+        Side::Us => TelnetAction::Will,
+        Side::Him => TelnetAction::Do,
+    }
+}
+
+fn disable_action(side: Side) -> TelnetAction {
+    match side {
+        Side::Us => TelnetAction::Wont,
+        Side::Him => TelnetAction::Dont,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_sends_immediately_from_no() {
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.request(TelnetAction::Will, TelnetOption::Echo),
+            Some(TelnetAction::Will)
+        );
+        assert_eq!(
+            n.request(TelnetAction::Do, TelnetOption::TerminalType),
+            Some(TelnetAction::Do)
+        );
+    }
+
+    #[test]
+    fn request_not_resent_while_pending() {
+        let mut n = Negotiator::new();
+        assert_eq!(n.request(TelnetAction::Will, TelnetOption::Echo), Some(TelnetAction::Will));
+        // Already WantYes, so a second enable request sends nothing.
+        assert_eq!(n.request(TelnetAction::Will, TelnetOption::Echo), None);
+    }
+
+    #[test]
+    fn locally_offered_will_is_confirmed_by_peer_do() {
+        // We offer WILL COMPRESS2 (e.g. offer_compression); the peer
+        // confirms with DO. That must resolve our `us` state to Yes and
+        // must NOT send another DO back (the chunk0-2 inversion bug).
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.request(TelnetAction::Will, TelnetOption::Compress2),
+            Some(TelnetAction::Will)
+        );
+        assert_eq!(n.receive(TelnetAction::Do, TelnetOption::Compress2, true), None);
+        assert_eq!(n.enabled(), vec![TelnetOption::Compress2]);
+    }
+
+    #[test]
+    fn locally_requested_do_is_confirmed_by_peer_will() {
+        // We ask DO TERMINAL-TYPE; the peer confirms with WILL. That
+        // resolves our `him` state, replying nothing further.
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.request(TelnetAction::Do, TelnetOption::TerminalType),
+            Some(TelnetAction::Do)
+        );
+        assert_eq!(
+            n.receive(TelnetAction::Will, TelnetOption::TerminalType, true),
+            None
+        );
+        assert_eq!(n.enabled(), vec![TelnetOption::TerminalType]);
+    }
+
+    #[test]
+    fn unsolicited_peer_will_is_accepted_with_do() {
+        // The peer offers WILL ECHO out of the blue; accepting it must
+        // reply DO, not echo WILL back.
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.receive(TelnetAction::Will, TelnetOption::Echo, true),
+            Some(TelnetAction::Do)
+        );
+        assert_eq!(n.enabled(), vec![TelnetOption::Echo]);
+    }
+
+    #[test]
+    fn unsolicited_peer_do_is_accepted_with_will() {
+        // The peer asks DO SUPPRESS-GO-AHEAD; accepting it must reply
+        // WILL, not echo DO back.
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.receive(TelnetAction::Do, TelnetOption::SuppressGoAhead, true),
+            Some(TelnetAction::Will)
+        );
+        assert_eq!(n.enabled(), vec![TelnetOption::SuppressGoAhead]);
+    }
+
+    #[test]
+    fn unsolicited_peer_will_is_refused_with_dont() {
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.receive(TelnetAction::Will, TelnetOption::Echo, false),
+            Some(TelnetAction::Dont)
+        );
+        assert_eq!(n.enabled(), Vec::<TelnetOption>::new());
+    }
+
+    #[test]
+    fn queued_opposite_request_is_sent_once_resolved() {
+        // While WantYes is outstanding, a Wont is queued; once the peer's
+        // Do resolves the WantYes to Yes, the queued disable fires.
+        let mut n = Negotiator::new();
+        assert_eq!(
+            n.request(TelnetAction::Will, TelnetOption::Echo),
+            Some(TelnetAction::Will)
+        );
+        assert_eq!(n.request(TelnetAction::Wont, TelnetOption::Echo), None);
+        assert_eq!(
+            n.receive(TelnetAction::Do, TelnetOption::Echo, true),
+            Some(TelnetAction::Wont)
+        );
+    }
+}
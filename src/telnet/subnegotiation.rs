@@ -0,0 +1,113 @@
+use bytes::{Buf, BytesMut};
+
+use super::negotiation::TelnetOption;
+
+/// The client's reported terminal dimensions (NAWS, `IAC SB WindowSize`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// A decoded `IAC SB <option> ... IAC SE` payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subnegotiation {
+    WindowSize(WindowSize),
+    TerminalType(String),
+    Unknown(Vec<u8>),
+}
+
+impl Subnegotiation {
+    fn decode(option: u8, payload: &[u8]) -> Self {
+        match (num::FromPrimitive::from_u8(option), payload) {
+            (Some(TelnetOption::WindowSize), [c1, c0, r1, r0]) => {
+                Self::WindowSize(WindowSize {
+                    cols: u16::from_be_bytes([*c1, *c0]),
+                    rows: u16::from_be_bytes([*r1, *r0]),
+                })
+            }
+
+            // Terminal type sends a leading IS (0) byte before the name
+            (Some(TelnetOption::TerminalType), [0, name @ ..]) => {
+                Self::TerminalType(String::from_utf8_lossy(name).into_owned())
+            }
+
+            _ => Self::Unknown(payload.to_vec()),
+        }
+    }
+}
+
+/// Scan for a complete `IAC SB <option> ... IAC SE` sequence at the start
+/// of `buffer`, un-escaping any doubled `IAC IAC` bytes in the payload, and
+/// decode it into a typed `Subnegotiation`. Consumes the bytes on success;
+/// leaves the buffer untouched if the sequence isn't complete yet.
+pub fn parse(buffer: &mut BytesMut) -> Option<Subnegotiation> {
+    if !buffer.starts_with(&[0xFF, 0xFA]) {
+        return None;
+    }
+
+    let option = *buffer.get(2)?;
+    let mut payload = Vec::new();
+    let mut i = 3;
+
+    loop {
+        match (buffer.get(i), buffer.get(i + 1)) {
+            (Some(&0xFF), Some(&0xF0)) => {
+                buffer.advance(i + 2);
+                return Some(Subnegotiation::decode(option, &payload));
+            }
+            (Some(&0xFF), Some(&0xFF)) => {
+                payload.push(0xFF);
+                i += 2;
+            }
+            (Some(&b), _) => {
+                payload.push(b);
+                i += 1;
+            }
+            (None, _) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_window_size() {
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 31, 0, 80, 0, 24, 0xFF, 0xF0][..]);
+        let result = parse(&mut buffer);
+        assert_eq!(
+            result,
+            Some(Subnegotiation::WindowSize(WindowSize { cols: 80, rows: 24 }))
+        );
+        assert_eq!(buffer, BytesMut::new());
+    }
+
+    #[test]
+    fn test_parse_terminal_type() {
+        let mut buffer = BytesMut::from(
+            &[0xFF, 0xFA, 24, 0, b'A', b'N', b'S', b'I', 0xFF, 0xF0][..],
+        );
+        let result = parse(&mut buffer);
+        assert_eq!(result, Some(Subnegotiation::TerminalType("ANSI".into())));
+    }
+
+    #[test]
+    fn test_parse_unescapes_doubled_iac() {
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 99, 0x01, 0xFF, 0xFF, 0x02, 0xFF, 0xF0][..]);
+        let result = parse(&mut buffer);
+        assert_eq!(
+            result,
+            Some(Subnegotiation::Unknown(vec![0x01, 0xFF, 0x02]))
+        );
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        let mut buffer = BytesMut::from(&[0xFF, 0xFA, 31, 0, 80][..]);
+        let result = parse(&mut buffer);
+        assert_eq!(result, None);
+        assert_eq!(buffer, BytesMut::from(&[0xFF, 0xFA, 31, 0, 80][..]));
+    }
+}